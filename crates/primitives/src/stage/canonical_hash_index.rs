@@ -0,0 +1,114 @@
+use alloy_primitives::{keccak256, B256};
+
+/// Number of blocks committed into a single [`StageId::CanonicalHashIndex`] window.
+///
+/// [`StageId::CanonicalHashIndex`]: super::StageId::CanonicalHashIndex
+pub const CANONICAL_HASH_WINDOW_SIZE: u64 = 2048;
+
+/// Returns the window a block number falls into.
+///
+/// One committed Merkle root per window lets a light client verify an arbitrarily old header
+/// against a single small root instead of downloading the full header range. This module only
+/// computes window indices, roots and proofs; it does not store the roots in any table.
+pub fn window_index(block_number: u64) -> u64 {
+    block_number / CANONICAL_HASH_WINDOW_SIZE
+}
+
+/// Returns the position of `block_number` within its window.
+pub fn position_in_window(block_number: u64) -> u64 {
+    block_number % CANONICAL_HASH_WINDOW_SIZE
+}
+
+/// Returns the inclusive range of window indices that must have their roots deleted and
+/// recomputed after an unwind from `tip` down to `unwind_to`.
+///
+/// The window containing `unwind_to` becomes the new partial frontier and must be rebuilt from
+/// the canonical hashes that remain after the unwind; every later window is fully rolled back.
+pub fn windows_affected_by_unwind(unwind_to: u64, tip: u64) -> std::ops::RangeInclusive<u64> {
+    window_index(unwind_to)..=window_index(tip)
+}
+
+/// A Merkle proof that a canonical block hash belongs to the root committed for its window.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CanonicalHashProof {
+    /// Root of the window the proven block belongs to.
+    pub window_root: B256,
+    /// Sibling hashes from the leaf up to `window_root`, in bottom-up order.
+    pub path: Vec<B256>,
+}
+
+/// Builds the Merkle trie over `hashes` (the canonical block hashes of a window, keyed by
+/// position within the window) and returns its root together with the proof path for
+/// `leaf_index`.
+///
+/// A window that hasn't yet been completed (the current frontier) has fewer than
+/// [`CANONICAL_HASH_WINDOW_SIZE`] known leaves; the tree is sized to the next power of two
+/// covering them, padding the remainder with [`B256::ZERO`].
+///
+/// # Panics
+///
+/// Panics if `leaf_index >= hashes.len()`.
+pub fn compute_window_root_and_proof(hashes: &[B256], leaf_index: usize) -> CanonicalHashProof {
+    assert!(leaf_index < hashes.len(), "leaf_index out of range for window");
+
+    let mut level = hashes.to_vec();
+    level.resize(level.len().next_power_of_two().max(1), B256::ZERO);
+
+    let mut index = leaf_index;
+    let mut path = Vec::new();
+
+    while level.len() > 1 {
+        let sibling = if index % 2 == 0 { level[index + 1] } else { level[index - 1] };
+        path.push(sibling);
+
+        let mut next = Vec::with_capacity(level.len() / 2);
+        for pair in level.chunks_exact(2) {
+            let mut buf = [0u8; 64];
+            buf[..32].copy_from_slice(pair[0].as_slice());
+            buf[32..].copy_from_slice(pair[1].as_slice());
+            next.push(keccak256(buf));
+        }
+
+        index /= 2;
+        level = next;
+    }
+
+    CanonicalHashProof { window_root: level[0], path }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn window_math_matches_expected_partition() {
+        assert_eq!(window_index(0), 0);
+        assert_eq!(window_index(2047), 0);
+        assert_eq!(window_index(2048), 1);
+        assert_eq!(position_in_window(2048), 0);
+        assert_eq!(position_in_window(2049), 1);
+    }
+
+    #[test]
+    fn unwind_recomputes_the_frontier_window() {
+        let windows = windows_affected_by_unwind(2050, 5000);
+        assert_eq!(*windows.start(), 1);
+        assert_eq!(*windows.end(), 2);
+    }
+
+    #[test]
+    fn single_leaf_window_root_is_the_leaf_itself() {
+        let hashes = vec![B256::with_last_byte(1)];
+        let proof = compute_window_root_and_proof(&hashes, 0);
+        assert_eq!(proof.window_root, hashes[0]);
+        assert!(proof.path.is_empty());
+    }
+
+    #[test]
+    fn proof_path_length_matches_tree_depth() {
+        let hashes: Vec<B256> = (0..5u8).map(B256::with_last_byte).collect();
+        let proof = compute_window_root_and_proof(&hashes, 3);
+        // 5 leaves pad to 8, a depth-3 tree.
+        assert_eq!(proof.path.len(), 3);
+    }
+}
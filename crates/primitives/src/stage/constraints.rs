@@ -0,0 +1,86 @@
+use super::StageId;
+
+/// Capability flags a stage declares about how it behaves within a pipeline.
+///
+/// A pipeline running a configured set of stages inherits the *strictest* combination of its
+/// stages' constraints via [`StageConstraints::merge_strictest`], so it can validate the set up
+/// front (e.g. reject a state-requiring subset when no state is available, or refuse to enable
+/// pruning across a stage that isn't prune-compatible).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StageConstraints {
+    /// Whether the stage reads or writes state (the hashed state / trie tables).
+    pub requires_state: bool,
+    /// Whether the stage writes to static files.
+    pub produces_static_files: bool,
+    /// Whether the stage can be safely unwound on its own, without unwinding sibling stages.
+    pub unwind_safe: bool,
+    /// Whether the stage can run correctly when history pruning is enabled.
+    pub prune_compatible: bool,
+}
+
+impl StageConstraints {
+    /// The identity element for [`merge_strictest`](Self::merge_strictest): imposes no
+    /// restriction of its own, since `false` is the permissive value for the OR'd flags
+    /// (`requires_state`, `produces_static_files`) but the *restrictive* value for the AND'd
+    /// ones (`unwind_safe`, `prune_compatible`). Custom [`StageId::Other`] stages also return
+    /// this, since the all-`false` output of `#[derive(Default)]` would otherwise poison
+    /// `merge_strictest` into marking a set containing any custom stage as unwind-unsafe and
+    /// prune-incompatible.
+    pub const PERMISSIVE: StageConstraints = StageConstraints {
+        requires_state: false,
+        produces_static_files: false,
+        unwind_safe: true,
+        prune_compatible: true,
+    };
+
+    /// Folds the constraints of `stages` into the strictest combined constraint: a flag that
+    /// restricts behavior when *any* stage sets it (`requires_state`, `produces_static_files`)
+    /// is OR'd, while a flag that only holds when *every* stage supports it (`unwind_safe`,
+    /// `prune_compatible`) is AND'd.
+    pub fn merge_strictest(stages: &[StageId]) -> StageConstraints {
+        stages.iter().map(StageId::constraints).fold(
+            StageConstraints::PERMISSIVE,
+            |acc, c| StageConstraints {
+                requires_state: acc.requires_state || c.requires_state,
+                produces_static_files: acc.produces_static_files || c.produces_static_files,
+                unwind_safe: acc.unwind_safe && c.unwind_safe,
+                prune_compatible: acc.prune_compatible && c.prune_compatible,
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merges_state_requirement_across_stages() {
+        let constraints = StageConstraints::merge_strictest(&[StageId::Headers, StageId::Execution]);
+        assert!(constraints.requires_state);
+        assert!(constraints.unwind_safe);
+    }
+
+    #[test]
+    fn prune_compatibility_is_strictest_across_hashing_stages() {
+        let constraints =
+            StageConstraints::merge_strictest(&[StageId::AccountHashing, StageId::TransactionLookup]);
+        assert!(!constraints.prune_compatible);
+    }
+
+    #[test]
+    fn empty_set_is_maximally_permissive() {
+        let constraints = StageConstraints::merge_strictest(&[]);
+        assert_eq!(constraints, StageConstraints::PERMISSIVE);
+    }
+
+    #[test]
+    fn other_stage_is_permissive_and_does_not_poison_the_merge() {
+        let constraints = StageId::Other("Custom").constraints();
+        assert_eq!(constraints, StageConstraints::PERMISSIVE);
+
+        let merged = StageConstraints::merge_strictest(&[StageId::Headers, StageId::Other("Custom")]);
+        assert!(merged.unwind_safe);
+        assert!(merged.prune_compatible);
+    }
+}
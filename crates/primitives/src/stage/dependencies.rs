@@ -0,0 +1,122 @@
+use super::StageId;
+use petgraph::graph::DiGraph;
+
+/// Error returned by [`resolve_order`] when the declared stage dependencies contain a cycle.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+#[error("cycle detected in stage dependency graph, stages not resolved: {stages:?}")]
+pub struct CycleError {
+    /// The stages that could not be ordered: those on a dependency cycle, plus any stage that
+    /// (transitively) depends on one.
+    pub stages: Vec<StageId>,
+}
+
+/// Computes a valid execution order for `stages` from their declared
+/// [`StageId::dependencies`], using Kahn's algorithm on the dependency DAG.
+///
+/// Returns a [`CycleError`] naming the stages still unresolved if the dependencies form a
+/// cycle.
+pub fn resolve_order(stages: &[StageId]) -> Result<Vec<StageId>, CycleError> {
+    resolve_order_with(stages, StageId::dependencies)
+}
+
+/// Core of [`resolve_order`], parameterized over how a stage's dependencies are looked up.
+///
+/// Pulled out so tests can inject a dependency graph that actually cycles: `StageId::dependencies`
+/// is a hardcoded match that can never produce a cycle through the public API, so the cycle
+/// branch below would otherwise be unreachable from any real caller.
+fn resolve_order_with(
+    stages: &[StageId],
+    deps_of: impl Fn(&StageId) -> &'static [StageId],
+) -> Result<Vec<StageId>, CycleError> {
+    let mut graph = DiGraph::<StageId, ()>::new();
+    let mut nodes = Vec::with_capacity(stages.len());
+    for &stage in stages {
+        nodes.push((stage, graph.add_node(stage)));
+    }
+
+    let node_for = |stage: &StageId| nodes.iter().find(|(s, _)| s == stage).map(|(_, n)| *n);
+
+    for &stage in stages {
+        let Some(to) = node_for(&stage) else { continue };
+        for dep in deps_of(&stage) {
+            if let Some(from) = node_for(dep) {
+                graph.add_edge(from, to, ());
+            }
+        }
+    }
+
+    let mut in_degree = vec![0usize; nodes.len()];
+    for (idx, (_, node)) in nodes.iter().enumerate() {
+        in_degree[idx] = graph.neighbors_directed(*node, petgraph::Incoming).count();
+    }
+
+    let mut queue: Vec<usize> =
+        (0..nodes.len()).filter(|&idx| in_degree[idx] == 0).collect();
+    let mut order = Vec::with_capacity(nodes.len());
+    let mut visited = vec![false; nodes.len()];
+
+    while let Some(idx) = queue.pop() {
+        if visited[idx] {
+            continue
+        }
+        visited[idx] = true;
+        let (stage, node) = nodes[idx];
+        order.push(stage);
+
+        for succ in graph.neighbors_directed(node, petgraph::Outgoing) {
+            let succ_idx = nodes.iter().position(|(_, n)| *n == succ).unwrap();
+            in_degree[succ_idx] -= 1;
+            if in_degree[succ_idx] == 0 {
+                queue.push(succ_idx);
+            }
+        }
+    }
+
+    if order.len() != nodes.len() {
+        let remaining =
+            nodes.iter().enumerate().filter(|(idx, _)| !visited[*idx]).map(|(_, (s, _))| *s).collect();
+        return Err(CycleError { stages: remaining })
+    }
+
+    Ok(order)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_default_stage_set() {
+        let order = resolve_order(&StageId::ALL).unwrap();
+        assert_eq!(order.len(), StageId::ALL.len());
+
+        let pos = |stage: StageId| order.iter().position(|&s| s == stage).unwrap();
+        assert!(pos(StageId::Headers) < pos(StageId::Bodies));
+        assert!(pos(StageId::Bodies) < pos(StageId::SenderRecovery));
+        assert!(pos(StageId::SenderRecovery) < pos(StageId::Execution));
+        assert!(pos(StageId::Execution) < pos(StageId::Finish));
+        assert!(pos(StageId::CanonicalHashIndex) < pos(StageId::Finish));
+        assert!(pos(StageId::StateDiffSync) < pos(StageId::Finish));
+    }
+
+    #[test]
+    fn detects_cycle() {
+        // `StageId::dependencies` can never itself describe a cycle, so exercise the cycle
+        // branch by injecting an artificial A -> B -> A dependency between two custom stages.
+        let a = StageId::Other("A");
+        let b = StageId::Other("B");
+
+        let deps_of = |stage: &StageId| -> &'static [StageId] {
+            match stage {
+                StageId::Other("A") => &[StageId::Other("B")],
+                StageId::Other("B") => &[StageId::Other("A")],
+                _ => &[],
+            }
+        };
+
+        let err = resolve_order_with(&[a, b], deps_of).unwrap_err();
+        assert_eq!(err.stages.len(), 2);
+        assert!(err.stages.contains(&a));
+        assert!(err.stages.contains(&b));
+    }
+}
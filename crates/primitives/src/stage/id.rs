@@ -1,3 +1,5 @@
+use super::StageConstraints;
+
 /// Stage IDs for all known stages.
 ///
 /// For custom stages, use [`StageId::Other`]
@@ -10,12 +12,22 @@ pub enum StageId {
     StaticFile,
     /// Header stage in the process.
     Headers,
+    /// Canonical hash index stage in the process.
+    ///
+    /// Materializes windowed Merkle commitments over canonical block hashes so light clients
+    /// can verify an arbitrarily old header with a single succinct proof.
+    CanonicalHashIndex,
     /// Bodies stage in the process.
     Bodies,
     /// Sender recovery stage in the process.
     SenderRecovery,
     /// Execution stage in the process.
     Execution,
+    /// State diff sync stage in the process.
+    ///
+    /// Bootstraps or fast-forwards state by applying compact state diffs instead of
+    /// re-executing, for catching up from a trusted snapshot provider.
+    StateDiffSync,
     /// Merkle unwind stage in the process.
     MerkleUnwind,
     /// Account hashing stage in the process.
@@ -38,11 +50,13 @@ pub enum StageId {
 
 impl StageId {
     /// All supported Stages
-    pub const ALL: [StageId; 12] = [
+    pub const ALL: [StageId; 14] = [
         StageId::Headers,
+        StageId::CanonicalHashIndex,
         StageId::Bodies,
         StageId::SenderRecovery,
         StageId::Execution,
+        StageId::StateDiffSync,
         StageId::MerkleUnwind,
         StageId::AccountHashing,
         StageId::StorageHashing,
@@ -53,26 +67,17 @@ impl StageId {
         StageId::Finish,
     ];
 
-    /// Stages that require state.
-    pub const STATE_REQUIRED: [StageId; 7] = [
-        StageId::Execution,
-        StageId::MerkleUnwind,
-        StageId::AccountHashing,
-        StageId::StorageHashing,
-        StageId::MerkleExecute,
-        StageId::IndexStorageHistory,
-        StageId::IndexAccountHistory,
-    ];
-
     /// Return stage id formatted as string.
     pub fn as_str(&self) -> &str {
         match self {
             #[allow(deprecated)]
             StageId::StaticFile => "StaticFile",
             StageId::Headers => "Headers",
+            StageId::CanonicalHashIndex => "CanonicalHashIndex",
             StageId::Bodies => "Bodies",
             StageId::SenderRecovery => "SenderRecovery",
             StageId::Execution => "Execution",
+            StageId::StateDiffSync => "StateDiffSync",
             StageId::MerkleUnwind => "MerkleUnwind",
             StageId::AccountHashing => "AccountHashing",
             StageId::StorageHashing => "StorageHashing",
@@ -99,6 +104,102 @@ impl StageId {
     pub fn is_finish(&self) -> bool {
         matches!(self, StageId::Finish)
     }
+
+    /// Returns the stages that must complete before this stage can run.
+    ///
+    /// Custom [`StageId::Other`] stages have no declared dependencies; operators inserting
+    /// a custom stage are expected to order it explicitly via the stage set they build.
+    pub fn dependencies(&self) -> &'static [StageId] {
+        match self {
+            #[allow(deprecated)]
+            StageId::StaticFile => &[],
+            StageId::Headers => &[],
+            StageId::CanonicalHashIndex => &[StageId::Headers],
+            StageId::Bodies => &[StageId::Headers],
+            StageId::SenderRecovery => &[StageId::Bodies],
+            StageId::Execution => &[StageId::SenderRecovery, StageId::Bodies],
+            StageId::StateDiffSync => &[StageId::Execution],
+            StageId::MerkleUnwind => &[StageId::Execution],
+            StageId::AccountHashing => &[StageId::MerkleUnwind],
+            StageId::StorageHashing => &[StageId::MerkleUnwind],
+            StageId::MerkleExecute => &[StageId::AccountHashing, StageId::StorageHashing],
+            StageId::TransactionLookup => &[StageId::Execution],
+            StageId::IndexStorageHistory => &[StageId::Execution],
+            StageId::IndexAccountHistory => &[StageId::Execution],
+            StageId::Finish => &[
+                StageId::CanonicalHashIndex,
+                StageId::StateDiffSync,
+                StageId::MerkleExecute,
+                StageId::TransactionLookup,
+                StageId::IndexStorageHistory,
+                StageId::IndexAccountHistory,
+            ],
+            StageId::Other(_) => &[],
+        }
+    }
+
+    /// Returns the [`StageConstraints`] declared by this stage.
+    ///
+    /// Custom [`StageId::Other`] stages always get [`StageConstraints::PERMISSIVE`]; there is no
+    /// way to declare other constraints for them, so
+    /// [`StageConstraints::merge_strictest`] treats every custom stage as permissive.
+    pub fn constraints(&self) -> StageConstraints {
+        match self {
+            #[allow(deprecated)]
+            StageId::StaticFile => StageConstraints {
+                requires_state: false,
+                produces_static_files: true,
+                unwind_safe: true,
+                prune_compatible: true,
+            },
+            StageId::Headers | StageId::Bodies => StageConstraints {
+                requires_state: false,
+                produces_static_files: true,
+                unwind_safe: true,
+                prune_compatible: true,
+            },
+            StageId::CanonicalHashIndex => StageConstraints {
+                requires_state: false,
+                produces_static_files: false,
+                unwind_safe: true,
+                // The windowed roots this stage commits to are the commitments ancient-header
+                // proofs rely on, so they must survive history pruning.
+                prune_compatible: false,
+            },
+            StageId::SenderRecovery | StageId::TransactionLookup | StageId::Finish => {
+                StageConstraints {
+                    requires_state: false,
+                    produces_static_files: false,
+                    unwind_safe: true,
+                    prune_compatible: true,
+                }
+            }
+            StageId::Execution | StageId::IndexStorageHistory | StageId::IndexAccountHistory => {
+                StageConstraints {
+                    requires_state: true,
+                    produces_static_files: false,
+                    unwind_safe: true,
+                    prune_compatible: true,
+                }
+            }
+            StageId::StateDiffSync => StageConstraints {
+                requires_state: true,
+                produces_static_files: false,
+                unwind_safe: true,
+                prune_compatible: true,
+            },
+            StageId::MerkleUnwind |
+            StageId::AccountHashing |
+            StageId::StorageHashing |
+            StageId::MerkleExecute => StageConstraints {
+                requires_state: true,
+                produces_static_files: false,
+                unwind_safe: true,
+                prune_compatible: false,
+            },
+            StageId::Other(_) => StageConstraints::PERMISSIVE,
+        }
+    }
 }
 
 impl std::fmt::Display for StageId {
@@ -114,9 +215,11 @@ mod tests {
     #[test]
     fn stage_id_as_string() {
         assert_eq!(StageId::Headers.to_string(), "Headers");
+        assert_eq!(StageId::CanonicalHashIndex.to_string(), "CanonicalHashIndex");
         assert_eq!(StageId::Bodies.to_string(), "Bodies");
         assert_eq!(StageId::SenderRecovery.to_string(), "SenderRecovery");
         assert_eq!(StageId::Execution.to_string(), "Execution");
+        assert_eq!(StageId::StateDiffSync.to_string(), "StateDiffSync");
         assert_eq!(StageId::MerkleUnwind.to_string(), "MerkleUnwind");
         assert_eq!(StageId::AccountHashing.to_string(), "AccountHashing");
         assert_eq!(StageId::StorageHashing.to_string(), "StorageHashing");
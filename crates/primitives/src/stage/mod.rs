@@ -0,0 +1,17 @@
+mod id;
+pub use id::*;
+
+mod dependencies;
+pub use dependencies::*;
+
+mod constraints;
+pub use constraints::*;
+
+mod restart;
+pub use restart::*;
+
+mod canonical_hash_index;
+pub use canonical_hash_index::*;
+
+mod state_diff_sync;
+pub use state_diff_sync::*;
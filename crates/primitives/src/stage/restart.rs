@@ -0,0 +1,111 @@
+use super::StageId;
+
+/// Tracks resumable progress for a single stage across restarts within one pipeline run.
+///
+/// A stage failing partway through (OOM, I/O error) resumes from its last durably flushed
+/// `cursor` instead of restarting from scratch. The helper lives in memory only: it is not
+/// serializable and is not persisted alongside the stage's checkpoint, so its state does not
+/// survive a process shutdown.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StageRestartHelper {
+    stage_id: StageId,
+    /// Whether this stage still needs to run this pipeline pass.
+    should_run: bool,
+    /// Monotonically increasing progress cursor (block number or entity count) last durably
+    /// flushed by the stage.
+    cursor: u64,
+    /// Number of times the stage has been re-entered after a failure this pipeline pass.
+    restarts: u32,
+    /// Maximum restarts allowed before giving up with the original error.
+    max_restarts: u32,
+}
+
+impl StageRestartHelper {
+    /// Creates a fresh helper for `stage_id`, starting at cursor `0` with no restarts consumed.
+    pub fn new(stage_id: StageId, max_restarts: u32) -> Self {
+        Self { stage_id, should_run: true, cursor: 0, restarts: 0, max_restarts }
+    }
+
+    /// Returns the cursor a stage should resume from on entry.
+    pub fn resume_point(&self) -> u64 {
+        self.cursor
+    }
+
+    /// Returns whether the stage still needs to run this pipeline pass.
+    pub fn should_run(&self) -> bool {
+        self.should_run
+    }
+
+    /// Marks whether the stage still needs to run this pipeline pass.
+    pub fn set_should_run(&mut self, should_run: bool) {
+        self.should_run = should_run;
+    }
+
+    /// Advances the cursor after the stage durably flushes a committed batch.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cursor` moves backwards, since the cursor must be monotonically increasing.
+    pub fn advance(&mut self, cursor: u64) {
+        assert!(cursor >= self.cursor, "stage restart cursor must not move backwards");
+        self.cursor = cursor;
+    }
+
+    /// Records that the stage is being re-entered after `err` triggered a restart.
+    ///
+    /// Returns `Ok(())` if the stage should retry, or hands `err` straight back once
+    /// `max_restarts` has been exceeded so the runner can give up with the original error
+    /// instead of retrying again.
+    pub fn record_restart<E>(&mut self, err: E) -> Result<(), E> {
+        self.restarts += 1;
+        if self.restarts > self.max_restarts {
+            return Err(err)
+        }
+        Ok(())
+    }
+}
+
+/// Returns the default `max_restarts` policy for a stage: downloading stages
+/// ([`StageId::is_downloading_stage`]) tolerate more restarts since a transient network error is
+/// expected to be far more common than a genuine unwind-worthy failure.
+pub fn default_max_restarts(stage_id: &StageId) -> u32 {
+    if stage_id.is_downloading_stage() {
+        10
+    } else {
+        3
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resumes_from_last_advanced_cursor() {
+        let mut helper = StageRestartHelper::new(StageId::Execution, 3);
+        assert_eq!(helper.resume_point(), 0);
+        helper.advance(100);
+        assert_eq!(helper.resume_point(), 100);
+    }
+
+    #[test]
+    fn gives_up_after_max_restarts_with_the_original_error() {
+        let mut helper = StageRestartHelper::new(StageId::Execution, 2);
+        assert_eq!(helper.record_restart("oom"), Ok(()));
+        assert_eq!(helper.record_restart("io error"), Ok(()));
+        assert_eq!(helper.record_restart("disk full"), Err("disk full"));
+    }
+
+    #[test]
+    fn downloading_stages_get_a_more_lenient_default() {
+        assert!(default_max_restarts(&StageId::Headers) > default_max_restarts(&StageId::Execution));
+    }
+
+    #[test]
+    #[should_panic(expected = "must not move backwards")]
+    fn cursor_cannot_move_backwards() {
+        let mut helper = StageRestartHelper::new(StageId::Execution, 3);
+        helper.advance(10);
+        helper.advance(5);
+    }
+}
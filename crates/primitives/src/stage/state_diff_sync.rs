@@ -0,0 +1,266 @@
+use alloy_primitives::{Address, B256, U256};
+use std::collections::BTreeMap;
+
+/// The location a [`StateDiffPatch`] operation applies to: an account or one of its storage
+/// slots.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PatchKey {
+    /// An account's basic info (balance, nonce, code hash).
+    Account(Address),
+    /// A single storage slot of an account.
+    Storage(Address, B256),
+}
+
+/// A single keyed edit within a [`StateDiffPatch`], carrying both the value it replaces and the
+/// value it installs so the edit can be reversed on unwind without re-deriving it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PatchOp {
+    /// Introduces a previously-absent account or slot.
+    Add {
+        /// The value installed by this edit.
+        value: U256,
+    },
+    /// Replaces an existing value with a new one.
+    Change {
+        /// The value this edit replaces.
+        old: U256,
+        /// The value this edit installs.
+        new: U256,
+    },
+    /// Removes an existing account or slot.
+    Delete {
+        /// The value this edit removes.
+        old: U256,
+    },
+}
+
+impl PatchOp {
+    /// Returns the inverse of this operation, for reversing an applied patch on unwind.
+    pub fn reversed(&self) -> PatchOp {
+        match *self {
+            PatchOp::Add { value } => PatchOp::Delete { old: value },
+            PatchOp::Change { old, new } => PatchOp::Change { old: new, new: old },
+            PatchOp::Delete { old } => PatchOp::Add { value: old },
+        }
+    }
+
+    /// The value this edit expects to find already in place, or `None` if the key didn't exist
+    /// beforehand.
+    fn pre(&self) -> Option<U256> {
+        match *self {
+            PatchOp::Add { .. } => None,
+            PatchOp::Change { old, .. } | PatchOp::Delete { old } => Some(old),
+        }
+    }
+
+    /// The value this edit leaves in place, or `None` if the key no longer exists afterward.
+    fn post(&self) -> Option<U256> {
+        match *self {
+            PatchOp::Add { value } | PatchOp::Change { new: value, .. } => Some(value),
+            PatchOp::Delete { .. } => None,
+        }
+    }
+}
+
+/// A compact state diff: an ordered set of keyed edits that transitions state from
+/// `pre_state_hash` to `post_state_hash`, modeled as a reverse-ed script of operations. A patch
+/// carries no signature; the pre/post content hashes are what tie it to its base.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StateDiffPatch {
+    /// Content hash of the state this patch expects to be applied on top of.
+    pub pre_state_hash: B256,
+    /// Content hash of the state this patch produces once applied.
+    pub post_state_hash: B256,
+    /// The edits that make up this patch, in application order.
+    pub ops: Vec<(PatchKey, PatchOp)>,
+}
+
+/// Returned by [`merge_patches`] when a patch's declared pre-state hash doesn't match the
+/// running hash of the patches applied so far, meaning the stage is applying patches against
+/// the wrong base and must abort rather than corrupt state.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+#[error("state diff patch {index} expected pre-state {expected}, but running state is {actual}")]
+pub struct PreStateMismatchError {
+    /// Index of the offending patch within the list passed to [`merge_patches`].
+    pub index: usize,
+    /// The pre-state hash the patch declared.
+    pub expected: B256,
+    /// The post-state hash of the previously applied patch (or the supplied base hash).
+    pub actual: B256,
+}
+
+/// Merges an ordered list of `patches` on top of `base_hash` into a single resolved set of
+/// edits, verifying each patch's declared pre-state hash against the running hash before
+/// applying it.
+///
+/// A key touched by more than one patch resolves to a composite op built from the *first*
+/// patch's pre-value and the *last* patch's post-value — not by overwriting with whichever raw
+/// op was written most recently, which would lose intermediate existence changes (e.g. an
+/// `Add` followed by a `Change` must still collapse to an `Add`, since the key didn't exist
+/// before either patch touched it).
+///
+/// Returns the resolved edits in first-touched order, along with the final post-state hash.
+pub fn merge_patches(
+    base_hash: B256,
+    patches: &[StateDiffPatch],
+) -> Result<(Vec<(PatchKey, PatchOp)>, B256), PreStateMismatchError> {
+    let mut running_hash = base_hash;
+    let mut first_pre: BTreeMap<PatchKey, Option<U256>> = BTreeMap::new();
+    let mut last_post: BTreeMap<PatchKey, Option<U256>> = BTreeMap::new();
+    let mut order: Vec<PatchKey> = Vec::new();
+
+    for (index, patch) in patches.iter().enumerate() {
+        if patch.pre_state_hash != running_hash {
+            return Err(PreStateMismatchError {
+                index,
+                expected: patch.pre_state_hash,
+                actual: running_hash,
+            })
+        }
+
+        for &(key, op) in &patch.ops {
+            first_pre.entry(key).or_insert_with(|| {
+                order.push(key);
+                op.pre()
+            });
+            last_post.insert(key, op.post());
+        }
+
+        running_hash = patch.post_state_hash;
+    }
+
+    let merged = order
+        .into_iter()
+        .filter_map(|key| {
+            let op = match (first_pre[&key], last_post[&key]) {
+                (None, Some(new)) => PatchOp::Add { value: new },
+                (Some(old), Some(new)) => PatchOp::Change { old, new },
+                (Some(old), None) => PatchOp::Delete { old },
+                // Introduced and removed again across the merged patches: no net edit.
+                (None, None) => return None,
+            };
+            Some((key, op))
+        })
+        .collect();
+
+    Ok((merged, running_hash))
+}
+
+/// Reverses a resolved set of edits for unwinding, applying [`PatchOp::reversed`] to each and
+/// replaying them in reverse order so later overlapping edits are undone before earlier ones.
+pub fn reverse_merged_ops(merged: &[(PatchKey, PatchOp)]) -> Vec<(PatchKey, PatchOp)> {
+    merged.iter().rev().map(|&(key, op)| (key, op.reversed())).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account(byte: u8) -> Address {
+        Address::with_last_byte(byte)
+    }
+
+    #[test]
+    fn overlapping_key_collapses_to_first_pre_and_last_post() {
+        let key = PatchKey::Account(account(1));
+        let patches = vec![
+            StateDiffPatch {
+                pre_state_hash: B256::ZERO,
+                post_state_hash: B256::with_last_byte(1),
+                ops: vec![(key, PatchOp::Add { value: U256::from(1) })],
+            },
+            StateDiffPatch {
+                pre_state_hash: B256::with_last_byte(1),
+                post_state_hash: B256::with_last_byte(2),
+                ops: vec![(key, PatchOp::Change { old: U256::from(1), new: U256::from(2) })],
+            },
+        ];
+
+        // The key didn't exist before patch1's `Add`, so even though patch2 touches it with a
+        // `Change`, the merged op must still be an `Add` (not a `Change`) or unwind would
+        // "restore" a value the key never had.
+        let (merged, final_hash) = merge_patches(B256::ZERO, &patches).unwrap();
+        assert_eq!(merged, vec![(key, PatchOp::Add { value: U256::from(2) })]);
+        assert_eq!(final_hash, B256::with_last_byte(2));
+
+        let reversed = reverse_merged_ops(&merged);
+        assert_eq!(reversed, vec![(key, PatchOp::Delete { old: U256::from(2) })]);
+    }
+
+    #[test]
+    fn delete_then_readd_collapses_to_change_with_original_value() {
+        let key = PatchKey::Account(account(2));
+        let patches = vec![
+            StateDiffPatch {
+                pre_state_hash: B256::ZERO,
+                post_state_hash: B256::with_last_byte(1),
+                ops: vec![(key, PatchOp::Delete { old: U256::from(5) })],
+            },
+            StateDiffPatch {
+                pre_state_hash: B256::with_last_byte(1),
+                post_state_hash: B256::with_last_byte(2),
+                ops: vec![(key, PatchOp::Add { value: U256::from(7) })],
+            },
+        ];
+
+        // The key existed with value 5 before either patch touched it, so unwind must restore
+        // 5 — not the stale `old` captured by the second patch's `Add` (which has none).
+        let (merged, _) = merge_patches(B256::ZERO, &patches).unwrap();
+        assert_eq!(merged, vec![(key, PatchOp::Change { old: U256::from(5), new: U256::from(7) })]);
+
+        let reversed = reverse_merged_ops(&merged);
+        assert_eq!(reversed, vec![(key, PatchOp::Change { old: U256::from(7), new: U256::from(5) })]);
+    }
+
+    #[test]
+    fn add_then_delete_nets_out_to_no_edit() {
+        let key = PatchKey::Account(account(3));
+        let patches = vec![
+            StateDiffPatch {
+                pre_state_hash: B256::ZERO,
+                post_state_hash: B256::with_last_byte(1),
+                ops: vec![(key, PatchOp::Add { value: U256::from(1) })],
+            },
+            StateDiffPatch {
+                pre_state_hash: B256::with_last_byte(1),
+                post_state_hash: B256::with_last_byte(2),
+                ops: vec![(key, PatchOp::Delete { old: U256::from(1) })],
+            },
+        ];
+
+        let (merged, _) = merge_patches(B256::ZERO, &patches).unwrap();
+        assert!(merged.is_empty());
+    }
+
+    #[test]
+    fn rejects_patch_applied_to_wrong_base() {
+        let patches = vec![StateDiffPatch {
+            pre_state_hash: B256::with_last_byte(99),
+            post_state_hash: B256::with_last_byte(1),
+            ops: vec![],
+        }];
+
+        let err = merge_patches(B256::ZERO, &patches).unwrap_err();
+        assert_eq!(err.index, 0);
+        assert_eq!(err.expected, B256::with_last_byte(99));
+        assert_eq!(err.actual, B256::ZERO);
+    }
+
+    #[test]
+    fn reversing_undoes_in_reverse_order() {
+        let key = PatchKey::Storage(account(1), B256::ZERO);
+        let other = PatchKey::Account(account(2));
+        let merged = vec![
+            (key, PatchOp::Change { old: U256::from(5), new: U256::from(9) }),
+            (other, PatchOp::Add { value: U256::from(3) }),
+        ];
+        let reversed = reverse_merged_ops(&merged);
+        assert_eq!(
+            reversed,
+            vec![
+                (other, PatchOp::Delete { old: U256::from(3) }),
+                (key, PatchOp::Change { old: U256::from(9), new: U256::from(5) }),
+            ]
+        );
+    }
+}